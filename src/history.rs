@@ -0,0 +1,506 @@
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+/// A cell containing a current value ([T]) and a bounded, fixed-capacity
+/// history of the last `N` values it held.
+///
+/// `update` pushes the outgoing current value onto the history, evicting the
+/// oldest entry once it holds `N` values. [`MemoryCell`](crate::MemoryCell)
+/// is the `N == 1` case of this type, keeping only a single previous value.
+///
+/// # `const` constructors
+///
+/// [`new`](HistoryCell::new) and [`empty`](HistoryCell::empty) are `const
+/// fn`, so a `HistoryCell` (and thus a `MemoryCell`) can be built in a
+/// `const` or `static` item, as it could before this type existed.
+/// [`with_last`](HistoryCell::with_last) is not `const` — see its own docs
+/// for why.
+///
+/// # Examples
+///
+/// ```
+/// use memcell::HistoryCell;
+///
+/// let mut cell = HistoryCell::<u32, 3>::new(1);
+/// cell.update(2);
+/// cell.update(3);
+/// cell.update(4);
+///
+/// // The history now holds its full 3 values, most recent first; one more
+/// // `update` would evict `1`.
+/// assert_eq!(cell.history().collect::<Vec<_>>(), vec![&3, &2, &1]);
+/// assert_eq!(cell.nth_last(0), Some(&3));
+/// assert_eq!(cell.last(), Some(&3));
+/// ```
+pub struct HistoryCell<T, const N: usize> {
+    current: Option<T>,
+    history: [MaybeUninit<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> HistoryCell<T, N> {
+    /// Create a new `HistoryCell` with the given value and an empty history.
+    pub const fn new(current: T) -> Self {
+        Self {
+            current: Some(current),
+            history: Self::uninit_history(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Create an empty `HistoryCell` with no current value yet, to be filled
+    /// in later with [`get_or_init`](HistoryCell::get_or_init).
+    pub const fn empty() -> Self {
+        Self {
+            current: None,
+            history: Self::uninit_history(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    const fn uninit_history() -> [MaybeUninit<T>; N] {
+        // SAFETY: an array of `MaybeUninit<T>` is valid in any bit pattern,
+        // so it never needs to be actually initialized.
+        unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() }
+    }
+
+    /// Set the current value as the most recent history entry, then set the
+    /// current value to the given argument, evicting the oldest history
+    /// entry if the history is already at its capacity of `N`.
+    ///
+    /// ```
+    /// use memcell::HistoryCell;
+    ///
+    /// let mut cell = HistoryCell::<u32, 2>::new(1);
+    /// cell.update(2);
+    /// cell.update(3);
+    ///
+    /// assert_eq!(cell.history().collect::<Vec<_>>(), vec![&2, &1]);
+    /// ```
+    pub fn update(&mut self, new: T) {
+        if let Some(old) = self.current.replace(new) {
+            self.push_history(old);
+        }
+    }
+
+    fn push_history(&mut self, value: T) {
+        if N == 0 {
+            return;
+        }
+
+        if self.len == N {
+            // SAFETY: a full history always holds an initialized value at
+            // `head`, the slot we are about to overwrite.
+            unsafe { ptr::drop_in_place(self.history[self.head].as_mut_ptr()) };
+        } else {
+            self.len += 1;
+        }
+
+        self.history[self.head] = MaybeUninit::new(value);
+        self.head = (self.head + 1) % N;
+    }
+
+    /// The ring-buffer index of the `n`th-most-recent history entry, valid
+    /// whenever `n < self.len` (which implies `N > 0`).
+    const fn slot_index(&self, n: usize) -> usize {
+        (self.head + N - 1 - n) % N
+    }
+
+    /// Get the `n`th-most-recent history entry (`0` is the most recent),
+    /// or `None` if the history holds fewer than `n + 1` entries.
+    ///
+    /// ```
+    /// use memcell::HistoryCell;
+    ///
+    /// let mut cell = HistoryCell::<u32, 2>::new(1);
+    /// cell.update(2);
+    ///
+    /// assert_eq!(cell.nth_last(0), Some(&1));
+    /// assert_eq!(cell.nth_last(1), None);
+    /// ```
+    pub const fn nth_last(&self, n: usize) -> Option<&T> {
+        if n >= self.len {
+            return None;
+        }
+
+        let idx = self.slot_index(n);
+        // SAFETY: `n < self.len`, so `idx` is one of the `len` slots just
+        // behind `head`, which are always initialized.
+        Some(unsafe { &*self.history[idx].as_ptr() })
+    }
+
+    /// Get the most recent history entry. Sugar for `nth_last(0)`.
+    pub const fn last(&self) -> Option<&T> {
+        self.nth_last(0)
+    }
+
+    /// Iterate over the history, most-recent first.
+    ///
+    /// ```
+    /// use memcell::HistoryCell;
+    ///
+    /// let mut cell = HistoryCell::<u32, 3>::new(1);
+    /// cell.update(2);
+    /// cell.update(3);
+    ///
+    /// assert_eq!(cell.history().collect::<Vec<_>>(), vec![&2, &1]);
+    /// ```
+    pub fn history(&self) -> impl Iterator<Item = &T> {
+        (0..self.len).map(move |n| self.nth_last(n).expect("n < self.len is always valid"))
+    }
+
+    /// Get whether this `HistoryCell` contains at least one history entry.
+    pub const fn has_previous(&self) -> bool {
+        self.len > 0
+    }
+
+    /// Get the current value contained within this `HistoryCell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell was constructed with
+    /// [`empty`](HistoryCell::empty) and never initialized via
+    /// [`get_or_init`](HistoryCell::get_or_init).
+    pub const fn current(&self) -> &T {
+        match self.current.as_ref() {
+            Some(current) => current,
+            None => panic!("HistoryCell::current called on an uninitialized cell"),
+        }
+    }
+
+    /// Peek at the current value without forcing it, returning `None` if the
+    /// cell was constructed [`empty`](HistoryCell::empty) and has not been
+    /// initialized yet.
+    pub const fn current_opt(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    /// Get the current value, initializing it with `f` on first access.
+    pub fn get_or_init(&mut self, f: impl FnOnce() -> T) -> &T {
+        self.current.get_or_insert_with(f)
+    }
+
+    /// Remove and return the most recent history entry, if any, adjusting
+    /// the ring buffer so it is not read or dropped again.
+    fn take_last_entry(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let idx = self.slot_index(0);
+        // SAFETY: `idx` holds an initialized value, and updating `head`/`len`
+        // below ensures it won't be read or dropped again.
+        let value = unsafe { self.history[idx].as_ptr().read() };
+        self.head = idx;
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<T> HistoryCell<T, 1> {
+    /// Create a new `HistoryCell` containing the given previous value.
+    ///
+    /// Unlike [`new`](HistoryCell::new), this cannot be `const` on stable
+    /// Rust: matching `last` by value to decide whether to drop it would
+    /// require dropping a generic `T` at compile time, which the compiler
+    /// does not allow.
+    pub fn with_last(current: T, last: Option<T>) -> Self {
+        let mut cell = Self::new(current);
+        if let Some(last) = last {
+            cell.push_history(last);
+        }
+        cell
+    }
+
+    /// Take the current value contained within this `HistoryCell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell was constructed with
+    /// [`empty`](HistoryCell::empty) and never initialized via
+    /// [`get_or_init`](HistoryCell::get_or_init).
+    pub fn take_current(mut self) -> T {
+        self.current
+            .take()
+            .expect("HistoryCell::take_current called on an uninitialized cell")
+    }
+
+    /// Take the previous value contained within this `HistoryCell`.
+    pub fn take_last(mut self) -> Option<T> {
+        self.take_last_entry()
+    }
+
+    /// Take both the current and last value of this `HistoryCell` and
+    /// return them in a tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell was constructed with
+    /// [`empty`](HistoryCell::empty) and never initialized via
+    /// [`get_or_init`](HistoryCell::get_or_init).
+    pub fn take_both(mut self) -> (T, Option<T>) {
+        let current = self
+            .current
+            .take()
+            .expect("HistoryCell::take_both called on an uninitialized cell");
+        let last = self.take_last_entry();
+        (current, last)
+    }
+
+    /// Roll back to the previous value, swapping `current` and `last`.
+    ///
+    /// Returns `true` if there was a previous value to restore. If `last` is
+    /// `None`, this is a no-op that returns `false`, so the current value is
+    /// never dropped. Calling this repeatedly toggles between the two most
+    /// recently stored values.
+    ///
+    /// ```
+    /// use memcell::MemoryCell;
+    ///
+    /// let mut cell = MemoryCell::new(5);
+    /// cell.update(10);
+    ///
+    /// assert!(cell.revert());
+    /// assert_eq!(cell.current(), &5);
+    /// assert_eq!(cell.last(), Some(&10));
+    ///
+    /// assert!(cell.revert());
+    /// assert_eq!(cell.current(), &10);
+    /// assert_eq!(cell.last(), Some(&5));
+    /// ```
+    pub fn revert(&mut self) -> bool {
+        match self.take_last_entry() {
+            Some(last) => {
+                if let Some(old_current) = self.current.replace(last) {
+                    self.push_history(old_current);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Compute the new current value from a reference to the old one, then
+    /// perform the usual current-to-last rotation, mirroring `Cell::update`.
+    ///
+    /// ```
+    /// use memcell::MemoryCell;
+    ///
+    /// let mut cell = MemoryCell::new(5);
+    /// cell.update_with(|old| old + 1);
+    ///
+    /// assert_eq!(cell.current(), &6);
+    /// assert_eq!(cell.last(), Some(&5));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell was constructed with
+    /// [`empty`](HistoryCell::empty) and never initialized via
+    /// [`get_or_init`](HistoryCell::get_or_init).
+    pub fn update_with(&mut self, f: impl FnOnce(&T) -> T) {
+        let new = f(self.current());
+        self.update(new);
+    }
+
+    /// Apply `f` to both `current` and `last`, producing a `HistoryCell` of
+    /// the transformed type.
+    ///
+    /// ```
+    /// use memcell::MemoryCell;
+    ///
+    /// let mut cell = MemoryCell::new(5);
+    /// cell.update(10);
+    ///
+    /// let cell = cell.map(|n| n.to_string());
+    ///
+    /// assert_eq!(cell.current(), "10");
+    /// assert_eq!(cell.last(), Some(&"5".to_string()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell was constructed with
+    /// [`empty`](HistoryCell::empty) and never initialized via
+    /// [`get_or_init`](HistoryCell::get_or_init).
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> HistoryCell<U, 1> {
+        let (current, last) = self.take_both();
+        HistoryCell::with_last(f(current), last.map(f))
+    }
+}
+
+impl<T, const N: usize> Drop for HistoryCell<T, N> {
+    fn drop(&mut self) {
+        for n in 0..self.len {
+            let idx = self.slot_index(n);
+            // SAFETY: every `n` in `0..self.len` maps to an initialized slot.
+            unsafe { ptr::drop_in_place(self.history[idx].as_mut_ptr()) };
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for HistoryCell<T, N> {
+    fn clone(&self) -> Self {
+        let mut history = Self::uninit_history();
+        for n in 0..self.len {
+            let idx = self.slot_index(n);
+            // SAFETY: every `n` in `0..self.len` maps to an initialized slot.
+            let value = unsafe { (*self.history[idx].as_ptr()).clone() };
+            history[idx] = MaybeUninit::new(value);
+        }
+
+        Self {
+            current: self.current.clone(),
+            history,
+            head: self.head,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for HistoryCell<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistoryCell")
+            .field("current", &self.current)
+            .field("history", &self.history().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HistoryCell, MemoryCell};
+
+    #[test]
+    fn update_cell() {
+        let mut cell = MemoryCell::new(5);
+        cell.update(10);
+
+        assert_eq!(cell.current_opt(), Some(&10));
+        assert_eq!(cell.last(), Some(&5));
+    }
+
+    #[test]
+    fn revert_swaps_current_and_last() {
+        let mut cell = MemoryCell::new(5);
+        cell.update(10);
+
+        assert!(cell.revert());
+        assert_eq!(cell.current_opt(), Some(&5));
+        assert_eq!(cell.last(), Some(&10));
+    }
+
+    #[test]
+    fn revert_without_previous_is_a_noop() {
+        let mut cell = MemoryCell::new(5);
+
+        assert!(!cell.revert());
+        assert_eq!(cell.current_opt(), Some(&5));
+        assert_eq!(cell.last(), None);
+    }
+
+    #[test]
+    fn get_or_init_fills_current_on_first_access_only() {
+        let mut cell = MemoryCell::empty();
+        assert_eq!(cell.current_opt(), None);
+
+        assert_eq!(cell.get_or_init(|| 5), &5);
+        assert_eq!(cell.get_or_init(|| 10), &5);
+        assert_eq!(cell.current_opt(), Some(&5));
+    }
+
+    #[test]
+    fn update_after_get_or_init_pushes_into_last() {
+        let mut cell = MemoryCell::empty();
+        cell.get_or_init(|| 5);
+        cell.update(10);
+
+        assert_eq!(cell.current_opt(), Some(&10));
+        assert_eq!(cell.last(), Some(&5));
+    }
+
+    #[test]
+    fn update_with_derives_new_value_from_old() {
+        let mut cell = MemoryCell::new(5);
+        cell.update_with(|old| old + 1);
+
+        assert_eq!(cell.current_opt(), Some(&6));
+        assert_eq!(cell.last(), Some(&5));
+    }
+
+    #[test]
+    fn map_transforms_current_and_last() {
+        let mut cell = MemoryCell::new(5);
+        cell.update(10);
+
+        let cell = cell.map(|n| n.to_string());
+
+        assert_eq!(cell.current_opt(), Some(&"10".to_string()));
+        assert_eq!(cell.last(), Some(&"5".to_string()));
+    }
+
+    #[test]
+    fn history_evicts_oldest_once_full() {
+        let mut cell = HistoryCell::<u32, 3>::new(1);
+        cell.update(2);
+        cell.update(3);
+        cell.update(4);
+        cell.update(5);
+
+        assert_eq!(cell.history().collect::<Vec<_>>(), vec![&4, &3, &2]);
+        assert_eq!(cell.nth_last(3), None);
+    }
+
+    #[test]
+    fn take_last_returns_the_previous_value() {
+        let mut cell = MemoryCell::new(5);
+        cell.update(10);
+
+        assert_eq!(cell.take_last(), Some(5));
+    }
+
+    #[test]
+    fn drop_runs_for_every_history_entry() {
+        use std::rc::Rc;
+
+        let marker = Rc::new(());
+        let mut cell = HistoryCell::<Rc<()>, 2>::new(Rc::clone(&marker));
+        cell.update(Rc::clone(&marker));
+        cell.update(Rc::clone(&marker));
+
+        assert_eq!(Rc::strong_count(&marker), 4);
+        drop(cell);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn clone_preserves_current_and_history() {
+        let mut cell = HistoryCell::<u32, 2>::new(1);
+        cell.update(2);
+        cell.update(3);
+
+        let cloned = cell.clone();
+
+        assert_eq!(cloned.current_opt(), cell.current_opt());
+        assert_eq!(
+            cloned.history().collect::<Vec<_>>(),
+            cell.history().collect::<Vec<_>>()
+        );
+    }
+
+    // `new` and `empty` are `const fn`; a regression here
+    // would only show up as a compile error, not a failing assertion.
+    const CONST_CELL: MemoryCell<u32> = MemoryCell::new(5);
+    const CONST_EMPTY: MemoryCell<u32> = MemoryCell::empty();
+
+    #[test]
+    fn const_constructors_are_usable_in_const_context() {
+        assert_eq!(CONST_CELL.current(), &5);
+        assert_eq!(CONST_CELL.last(), None);
+        assert_eq!(CONST_EMPTY.current_opt(), None);
+    }
+}
@@ -1,6 +1,14 @@
-use std::mem;
+mod atomic;
+mod history;
+#[cfg(feature = "serde")]
+mod serde_impl;
 
-/// A cell containing a value ([T]), and the last (previous) value stored in the cell.
+pub use atomic::AtomicMemoryCell;
+pub use history::HistoryCell;
+
+/// A cell containing a value, and the last (previous) value stored in the
+/// cell. This is the `N == 1` case of [`HistoryCell`]; see that type for the
+/// general, `N`-deep history variant.
 ///
 /// # Examples
 ///
@@ -13,150 +21,40 @@ use std::mem;
 /// cell.update(new_value);
 ///
 /// assert_eq!(cell.current(), &10);
-/// assert_eq!(cell.last(), &Some(5));
+/// assert_eq!(cell.last(), Some(&5));
 /// ```
-#[derive(Debug, Clone)]
-pub struct MemoryCell<T> {
-    current: T,
-    last_val: Option<T>,
-}
-
-impl<T> MemoryCell<T> {
-    /// Set the current value as the last value, then set the current value to the given argument.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let mut cell = MemoryCell::new(5_u32);
-    ///
-    /// let new_value = 10;
-    /// cell.update(new_value);
-    ///
-    /// assert_eq!(cell.current(), &10);
-    /// assert_eq!(cell.last(), &Some(5));
-    /// ```
-    pub fn update(&mut self, new: T) {
-        self.last_val = Some(mem::replace(&mut self.current, new));
-    }
-
-    /// Take the current value contained within this `MemoryCell`.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let cell = MemoryCell::new("Joe");
-    /// let data = cell.take_current();
-    ///
-    /// assert_eq!(data, "Joe");
-    /// ```
-    pub fn take_current(self) -> T {
-        self.current
-    }
-
-    /// Take the previous value contained within this `MemoryCell`.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let mut cell = MemoryCell::new(5);
-    /// cell.update(10);
-    ///
-    /// assert_eq!(cell.take_last(), Some(5));
-    /// ```
-    pub fn take_last(self) -> Option<T> {
-        self.last_val
-    }
-
-    /// Take both the current and last value of this `MemoryCell` and return them in a tuple.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let mut cell = MemoryCell::new(5);
-    ///
-    /// cell.update(10);
-    ///
-    /// let (new, old) = cell.take_both();
-    ///
-    /// assert_eq!(new, 10);
-    /// assert_eq!(old, Some(5));
-    /// ```
-    pub fn take_both(self) -> (T, Option<T>) {
-        (self.current, self.last_val)
-    }
-
-    /// Get whether this `MemoryCell` contains a previous value.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let mut cell = MemoryCell::new(5);
-    ///
-    /// assert!(!cell.has_previous());
-    ///
-    /// cell.update(10);
-    ///
-    /// assert!(cell.has_previous());
-    /// ```
-    pub const fn has_previous(&self) -> bool {
-        self.last_val.is_some()
-    }
-
-    /// Get the current value contained within this `MemoryCell`.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let cell = MemoryCell::new("Joe");
-    /// let data = cell.current();
-    ///
-    /// assert_eq!(data, &"Joe");
-    /// ```
-    pub const fn current(&self) -> &T {
-        &self.current
-    }
-
-    /// Get the last value contained in this `MemoryCell`.
-    ///
-    /// ```
-    /// use memcell::MemoryCell;
-    ///
-    /// let mut cell = MemoryCell::new(5);
-    /// cell.update(10);
-    ///
-    /// assert_eq!(cell.last(), &Some(5));
-    /// ```
-    pub const fn last(&self) -> &Option<T> {
-        &self.last_val
-    }
-
-    /// Create a new `MemoryCell` with the given value.
-    pub const fn new(current: T) -> Self {
-        Self {
-            current,
-            last_val: None,
-        }
-    }
-
-    /// Create a new `MemoryCell` containing the given previous value.
-    pub const fn with_last(current: T, last_val: Option<T>) -> Self {
-        Self { current, last_val }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::MemoryCell;
-
-    #[test]
-    fn update_cell() {
-        let old_value = 5;
-        let mut cell = MemoryCell::new(old_value);
-
-        let new_value = 10;
-        cell.update(new_value);
-
-        assert_eq!(cell.current, new_value);
-        assert_eq!(cell.last_val, Some(old_value));
-    }
-}
+///
+/// # Lazy initialization
+///
+/// A `MemoryCell` can also be constructed [`empty`](MemoryCell::empty) and
+/// have its current value filled in on first access with
+/// [`get_or_init`](MemoryCell::get_or_init), deferring expensive
+/// construction of the tracked value.
+///
+/// # Serde
+///
+/// With the `serde` cargo feature enabled, `MemoryCell<T>` implements
+/// [`serde::Serialize`] (when `T: Serialize`) and [`serde::Deserialize`] (when
+/// `T: Deserialize`), serializing as a struct with `current` and `last`
+/// fields so the full state of the cell, history slot included, round-trips.
+/// Serializing a cell whose current value has not been initialized yet
+/// fails.
+///
+/// # Breaking changes from the original `MemoryCell`
+///
+/// Rebasing `MemoryCell` onto [`HistoryCell`] is a semver-major change for
+/// three reasons, and crates depending on the old shape need a major-version
+/// bump, not just a changelog note:
+///
+/// - [`last`](HistoryCell::last) used to return `&Option<T>`; it now returns
+///   `Option<&T>`. Existing callers matching on or `.clone()`-ing the old
+///   `&Option<T>` will fail to compile and need updating.
+/// - [`current`](HistoryCell::current), [`take_current`](HistoryCell::take_current)
+///   and [`take_both`](HistoryCell::take_both) can now panic, where `current`
+///   used to be infallible. This is only reachable through the new
+///   [`empty`](HistoryCell::empty) constructor, so it cannot affect callers
+///   who only ever built cells with [`new`](HistoryCell::new) or
+///   [`with_last`](HistoryCell::with_last).
+/// - [`with_last`](HistoryCell::with_last) used to be a `const fn`; it no
+///   longer is, so calls to it in a `const` or `static` item fail to compile.
+pub type MemoryCell<T> = HistoryCell<T, 1>;
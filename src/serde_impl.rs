@@ -0,0 +1,73 @@
+//! Manual `serde` support for [`MemoryCell`], mirroring `lazycell`'s optional
+//! `serde_impl` module.
+//!
+//! A derive can't be used here: `MemoryCell` stores its current value
+//! lazily and its history in a ring buffer internally, but the public
+//! representation is a plain `{ current: T, last: Option<T> }` struct, so
+//! the two are bridged by hand below.
+
+use serde::ser::{Error as _, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::MemoryCell;
+
+impl<T: Serialize> Serialize for MemoryCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let current = self.current_opt().ok_or_else(|| {
+            S::Error::custom("cannot serialize a MemoryCell with no current value")
+        })?;
+
+        let mut state = serializer.serialize_struct("MemoryCell", 2)?;
+        state.serialize_field("current", current)?;
+        state.serialize_field("last", &self.last())?;
+        state.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MemoryCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "MemoryCell")]
+        struct Repr<T> {
+            current: T,
+            last: Option<T>,
+        }
+
+        Repr::deserialize(deserializer)
+            .map(|repr| MemoryCell::with_last(repr.current, repr.last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MemoryCell;
+
+    #[test]
+    fn roundtrip() {
+        let mut cell = MemoryCell::new(5);
+        cell.update(10);
+
+        let json = serde_json::to_string(&cell).unwrap();
+        let restored: MemoryCell<i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.current_opt(), Some(&10));
+        assert_eq!(restored.last(), Some(&5));
+    }
+
+    #[test]
+    fn roundtrip_empty_history() {
+        let cell = MemoryCell::new(5);
+
+        let json = serde_json::to_string(&cell).unwrap();
+        let restored: MemoryCell<i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.last(), None);
+    }
+
+    #[test]
+    fn serializing_an_uninitialized_cell_fails() {
+        let cell: MemoryCell<i32> = MemoryCell::empty();
+
+        assert!(serde_json::to_string(&cell).is_err());
+    }
+}